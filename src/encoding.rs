@@ -0,0 +1,151 @@
+//! Best-effort charset detection and transcoding to UTF-8 for input that
+//! isn't already valid UTF-8, modelled after charset-normalizer's "mess
+//! ratio" approach: decode the raw bytes with a handful of candidate
+//! encodings and keep whichever result looks cleanest.
+
+use encoding_rs::Encoding;
+
+/// Candidate encodings tried by `--encoding auto` when the input isn't
+/// already valid UTF-8, in preference order (used to break ties).
+const CANDIDATES: &[&str] = &[
+    "windows-1252",
+    "iso-8859-1",
+    "iso-8859-15",
+    "iso-8859-7",
+    "windows-1251",
+    "windows-1256",
+    "shift_jis",
+    "gb18030",
+    "euc-kr",
+    "utf-16le",
+    "utf-16be",
+];
+
+/// Decodes `buf` to a `String`, honoring `encoding` ("auto", or an
+/// `encoding_rs` label such as "windows-1252"). `auto` uses `buf` as-is
+/// when it is already valid UTF-8, honors a leading BOM if present, and
+/// otherwise falls back to mess-ratio scoring across `CANDIDATES`.
+///
+/// Returns the decoded text along with the lower-cased `encoding_rs`
+/// canonical name of the encoding that was actually used, so callers
+/// (e.g. the `--hint-*` subsystem) can act on what was really detected
+/// rather than the literal `--encoding` argument.
+pub fn decode(buf: &[u8], encoding: &str) -> (String, String) {
+    if encoding != "auto" {
+        let enc = Encoding::for_label(encoding.as_bytes())
+            .unwrap_or_else(|| panic!("Unknown encoding: {}", encoding));
+        // `decode` lets a leading BOM override `enc`; report what it used.
+        let (text, used, _) = enc.decode(buf);
+        return (text.into_owned(), used.name().to_lowercase());
+    }
+
+    if let Ok(text) = std::str::from_utf8(buf) {
+        return (text.to_string(), "utf-8".to_string());
+    }
+
+    if let Some((enc, bom_length)) = Encoding::for_bom(buf) {
+        let (text, _, _) = enc.decode(&buf[bom_length..]);
+        return (text.into_owned(), enc.name().to_lowercase());
+    }
+
+    detect_and_decode(buf)
+}
+
+/// Decodes `buf` with every candidate in `CANDIDATES` and keeps the result
+/// with the lowest mess ratio, breaking ties in favor of fewer distinct
+/// scripts (i.e. text that looks like a single coherent script).
+fn detect_and_decode(buf: &[u8]) -> (String, String) {
+    let mut best: Option<(String, String, f64, usize)> = None;
+    for label in CANDIDATES {
+        let Some(enc) = Encoding::for_label(label.as_bytes()) else {
+            continue;
+        };
+        let (text, _, _) = enc.decode(buf);
+        let text = text.into_owned();
+        let ratio = mess_ratio(&text);
+        let scripts = script_diversity(&text);
+        let better = match &best {
+            None => true,
+            Some((_, _, best_ratio, best_scripts)) => {
+                ratio < *best_ratio || (ratio == *best_ratio && scripts < *best_scripts)
+            }
+        };
+        if better {
+            best = Some((text, enc.name().to_lowercase(), ratio, scripts));
+        }
+    }
+    best.map(|(text, name, _, _)| (text, name))
+        .unwrap_or_else(|| (String::from_utf8_lossy(buf).into_owned(), "utf-8".to_string()))
+}
+
+/// Fraction of characters that look like decoding noise: Unicode
+/// replacement characters, stray control characters, or combining marks
+/// that aren't attached to a base character.
+fn mess_ratio(text: &str) -> f64 {
+    let mut bad = 0usize;
+    let mut total = 0usize;
+    let mut prev_attachable = false;
+    for c in text.chars() {
+        total += 1;
+        let is_combining = is_combining_mark(c);
+        if c == '\u{FFFD}' || (c.is_control() && !matches!(c, '\n' | '\r' | '\t')) {
+            bad += 1;
+        } else if is_combining && !prev_attachable {
+            bad += 1;
+        }
+        if !is_combining {
+            prev_attachable = c.is_alphanumeric();
+        }
+    }
+    bad as f64 / total.max(1) as f64
+}
+
+/// Counts the distinct Unicode script blocks present (ASCII letters count
+/// as Latin), used to
+/// break ties in favor of text that looks like a single coherent script.
+fn script_diversity(text: &str) -> usize {
+    let mut blocks: Vec<ScriptBlock> = Vec::new();
+    for c in text.chars() {
+        if let Some(block) = script_block(c) {
+            if !blocks.contains(&block) {
+                blocks.push(block);
+            }
+        }
+    }
+    blocks.len()
+}
+
+#[derive(PartialEq)]
+enum ScriptBlock {
+    Latin,
+    Cyrillic,
+    Greek,
+    Hebrew,
+    Arabic,
+    Han,
+    Hiragana,
+    Katakana,
+    Hangul,
+}
+
+fn script_block(c: char) -> Option<ScriptBlock> {
+    match c as u32 {
+        0x0041..=0x024F => Some(ScriptBlock::Latin),
+        0x0370..=0x03FF => Some(ScriptBlock::Greek),
+        0x0400..=0x04FF => Some(ScriptBlock::Cyrillic),
+        0x0590..=0x05FF => Some(ScriptBlock::Hebrew),
+        0x0600..=0x06FF => Some(ScriptBlock::Arabic),
+        0x3040..=0x309F => Some(ScriptBlock::Hiragana),
+        0x30A0..=0x30FF => Some(ScriptBlock::Katakana),
+        0x4E00..=0x9FFF => Some(ScriptBlock::Han),
+        0xAC00..=0xD7A3 => Some(ScriptBlock::Hangul),
+        _ => None,
+    }
+}
+
+fn is_combining_mark(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F
+    )
+}
@@ -0,0 +1,137 @@
+//! Structured (JSON / NDJSON) output support, as an alternative to the
+//! default delimiter-separated text printers in `main.rs`.
+
+use crate::reliability::MultiSummary;
+use lingua::Language;
+use serde::Serialize;
+
+/// Output mode selected via `--format`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// The original delimiter-separated text output.
+    Text,
+    /// A single JSON document (an object for single-text results, an array
+    /// of per-line objects for `--per-line`, or an object holding the
+    /// segments and the summary for `--multi`).
+    Json,
+    /// Newline-delimited JSON: one JSON object per input unit.
+    Jsonl,
+}
+
+#[derive(Serialize)]
+pub struct LanguageScore {
+    pub iso_code: String,
+    pub language_name: String,
+    pub confidence: f64,
+}
+
+#[derive(Serialize)]
+pub struct TextResult {
+    pub languages: Vec<LanguageScore>,
+}
+
+#[derive(Serialize)]
+pub struct LineResult {
+    pub line: String,
+    pub languages: Vec<LanguageScore>,
+}
+
+#[derive(Serialize)]
+pub struct MultiSegment {
+    pub start_index: usize,
+    pub end_index: usize,
+    pub iso_code: String,
+    pub text: String,
+}
+
+#[derive(Serialize)]
+pub struct MultiResult {
+    pub segments: Vec<MultiSegment>,
+    pub summary: MultiSummary,
+}
+
+/// A single `--multi --format jsonl` segment record, tagged so a
+/// line-by-line NDJSON reader can tell it apart from the trailing
+/// `SummaryRecord` without guessing from the fields present.
+#[derive(Serialize)]
+pub struct MultiSegmentRecord<'a> {
+    #[serde(rename = "type")]
+    pub record_type: &'static str,
+    #[serde(flatten)]
+    pub segment: &'a MultiSegment,
+}
+
+impl<'a> MultiSegmentRecord<'a> {
+    pub fn new(segment: &'a MultiSegment) -> Self {
+        MultiSegmentRecord {
+            record_type: "segment",
+            segment,
+        }
+    }
+}
+
+/// The trailing record of a `--multi --format jsonl` stream, tagged
+/// `"type":"summary"` to distinguish it from the segment records
+/// preceding it.
+#[derive(Serialize)]
+pub struct SummaryRecord {
+    #[serde(rename = "type")]
+    pub record_type: &'static str,
+    pub summary: MultiSummary,
+}
+
+impl SummaryRecord {
+    pub fn new(summary: MultiSummary) -> Self {
+        SummaryRecord {
+            record_type: "summary",
+            summary,
+        }
+    }
+}
+
+/// Reduces a detector's confidence distribution to the ranked list of
+/// languages that should be reported, honouring the same `--confidence`
+/// and `--all` semantics as the text printers.
+pub fn language_scores(
+    results: &[(Language, f64)],
+    confidence_threshold: Option<f64>,
+    all: bool,
+) -> Vec<LanguageScore> {
+    let mut scores = Vec::new();
+    for (language, confidence) in results {
+        if let Some(threshold) = confidence_threshold {
+            if *confidence < threshold {
+                continue;
+            }
+        }
+        scores.push(LanguageScore {
+            iso_code: language.iso_code_639_1().to_string(),
+            language_name: language.to_string(),
+            confidence: *confidence,
+        });
+        if !all {
+            break;
+        }
+    }
+    scores
+}
+
+pub fn line_result(
+    line: &str,
+    results: &[(Language, f64)],
+    confidence_threshold: Option<f64>,
+    all: bool,
+) -> LineResult {
+    LineResult {
+        line: line.to_string(),
+        languages: language_scores(results, confidence_threshold, all),
+    }
+}
+
+/// Serializes `value` as a single line of JSON and prints it to stdout.
+pub fn print_json_line(value: &impl Serialize) {
+    println!(
+        "{}",
+        serde_json::to_string(value).expect("result should be serializable to JSON")
+    );
+}
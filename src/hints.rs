@@ -0,0 +1,126 @@
+//! A priori detection hints that bias the final ranking, inspired by
+//! CLD2's `CLDHints`: a declared language, a TLD, and/or the input
+//! encoding can each nominate a language whose confidence is boosted
+//! before the distribution is renormalized.
+
+use lingua::{IsoCode639_1, Language};
+use std::str::FromStr;
+
+/// Multiplier applied to a hinted language's confidence before
+/// renormalization.
+const HINT_BOOST: f64 = 1.5;
+
+/// Prior knowledge about the expected language of the input, gathered
+/// from `--hint-lang`, `--hint-tld` and `--encoding`.
+#[derive(Default)]
+pub struct Hints {
+    lang: Option<Language>,
+    tld: Option<String>,
+    encoding: Option<String>,
+}
+
+impl Hints {
+    pub fn new(lang: Option<&str>, tld: Option<&str>) -> Self {
+        Hints {
+            lang: lang.map(|code| {
+                let iso_code = IsoCode639_1::from_str(code)
+                    .expect("Supported iso639-1 language code expected for --hint-lang");
+                Language::from_iso_code_639_1(&iso_code)
+            }),
+            tld: tld.map(|s| s.trim_start_matches('.').to_lowercase()),
+            encoding: None,
+        }
+    }
+
+    /// Attaches the encoding hint, in the resolved/detected form returned
+    /// by `encoding::decode` rather than the raw `--encoding` argument
+    /// (which is frequently just `"auto"`).
+    pub fn with_encoding(mut self, encoding: Option<&str>) -> Self {
+        self.encoding = encoding.map(|s| s.to_lowercase());
+        self
+    }
+
+    fn is_empty(&self) -> bool {
+        self.lang.is_none() && self.tld.is_none() && self.encoding.is_none()
+    }
+
+    /// Languages nominated by any configured hint.
+    fn hinted_languages(&self) -> Vec<Language> {
+        let mut languages = Vec::new();
+        languages.extend(self.lang);
+        languages.extend(self.tld.as_deref().and_then(tld_language));
+        languages.extend(self.encoding.as_deref().and_then(encoding_language));
+        languages
+    }
+
+    /// Reweights `results` (as returned by
+    /// `compute_language_confidence_values`) by boosting hinted languages
+    /// and renormalizing the distribution so confidences still sum to 1.
+    pub fn apply(&self, mut results: Vec<(Language, f64)>) -> Vec<(Language, f64)> {
+        if self.is_empty() || results.is_empty() {
+            return results;
+        }
+        let hinted = self.hinted_languages();
+        if hinted.is_empty() {
+            return results;
+        }
+        for (language, confidence) in results.iter_mut() {
+            if hinted.contains(language) {
+                *confidence *= HINT_BOOST;
+            }
+        }
+        let total: f64 = results.iter().map(|(_, confidence)| confidence).sum();
+        if total > 0.0 {
+            for (_, confidence) in results.iter_mut() {
+                *confidence /= total;
+            }
+        }
+        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        results
+    }
+}
+
+/// Maps a country-code TLD to its dominant language.
+fn tld_language(tld: &str) -> Option<Language> {
+    Some(match tld {
+        "de" | "at" => Language::German,
+        "fr" => Language::French,
+        "es" | "mx" => Language::Spanish,
+        "it" => Language::Italian,
+        "nl" | "be" => Language::Dutch,
+        "pt" | "br" => Language::Portuguese,
+        "ru" => Language::Russian,
+        "jp" => Language::Japanese,
+        "cn" => Language::Chinese,
+        "kr" => Language::Korean,
+        "pl" => Language::Polish,
+        "se" => Language::Swedish,
+        "no" => Language::Bokmal,
+        "dk" => Language::Danish,
+        "fi" => Language::Finnish,
+        "gr" => Language::Greek,
+        "tr" => Language::Turkish,
+        "ua" => Language::Ukrainian,
+        "cz" => Language::Czech,
+        "hu" => Language::Hungarian,
+        "ro" => Language::Romanian,
+        "vn" => Language::Vietnamese,
+        "uk" | "us" | "au" | "ca" => Language::English,
+        _ => return None,
+    })
+}
+
+/// Maps an encoding label to the language whose script it was designed
+/// to carry (e.g. Shift-JIS implies Japanese).
+fn encoding_language(encoding: &str) -> Option<Language> {
+    Some(match encoding {
+        "shift_jis" | "shift-jis" | "sjis" | "euc-jp" => Language::Japanese,
+        "euc-kr" | "cp949" => Language::Korean,
+        "gb18030" | "gbk" | "gb2312" | "big5" => Language::Chinese,
+        "windows-1251" | "koi8-r" | "iso-8859-5" => Language::Russian,
+        "windows-1253" | "iso-8859-7" => Language::Greek,
+        "windows-1256" | "iso-8859-6" => Language::Arabic,
+        "windows-1255" | "iso-8859-8" => Language::Hebrew,
+        _ => return None,
+    })
+}
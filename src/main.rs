@@ -1,8 +1,23 @@
+mod encoding;
+mod format;
+mod hints;
+mod reliability;
+mod serve;
+
 use clap::Parser;
+use format::OutputFormat;
 use lingua::{DetectionResult, IsoCode639_1, Language, LanguageDetectorBuilder};
-use std::io::{self, BufRead, Read};
+use std::io::{self, Read};
 use std::str::FromStr;
 
+/// Default `--top-n` used by `--serve` requests, which don't expose the
+/// flag individually per request.
+pub(crate) const DEFAULT_TOP_N: usize = 3;
+/// Default `--reliability-threshold` used by `--serve` requests.
+pub(crate) const DEFAULT_RELIABILITY_THRESHOLD: f64 = 0.8;
+/// Default minimum relative distance used by `--serve` requests.
+pub(crate) const DEFAULT_MINIMUM_RELATIVE_DISTANCE: f64 = 0.0;
+
 #[derive(Parser)]
 #[command(version, about)]
 struct Args {
@@ -74,6 +89,56 @@ struct Args {
     #[arg(short, long, default_value = "\t")]
     delimiter: String,
 
+    #[arg(
+        short,
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Output format: 'text' for the delimited format (default), 'json' for a single JSON document, or 'jsonl' for newline-delimited JSON (one object per input unit)"
+    )]
+    format: OutputFormat,
+
+    #[arg(
+        short = 'e',
+        long,
+        default_value = "auto",
+        help = "Input encoding: 'auto' to detect automatically (used as-is if already valid UTF-8, otherwise guessed via mess-ratio scoring across common encodings, honoring a BOM if present), or an explicit encoding_rs label such as 'windows-1252' or 'shift_jis'"
+    )]
+    encoding: String,
+
+    #[arg(
+        long,
+        help = "A priori declared language (ISO 639-1), e.g. from an HTTP Content-Language header, to bias the ranking toward"
+    )]
+    hint_lang: Option<String>,
+
+    #[arg(
+        long,
+        help = "A priori country-code top-level domain (e.g. 'de', 'jp') to bias the ranking toward its dominant language"
+    )]
+    hint_tld: Option<String>,
+
+    #[arg(
+        short = 't',
+        long,
+        default_value_t = DEFAULT_TOP_N,
+        help = "Number of top languages to report in the --multi reliability summary"
+    )]
+    top_n: usize,
+
+    #[arg(
+        long,
+        default_value_t = DEFAULT_RELIABILITY_THRESHOLD,
+        help = "Minimum top-language coverage (0.0-1.0) required for the --multi result to be marked 'reliable'"
+    )]
+    reliability_threshold: f64,
+
+    #[arg(
+        long,
+        help = "Run as a persistent server instead of processing input once: preloads the models, then classifies one newline-delimited JSON request per line ({\"text\":..,\"multi\":..,\"confidence\":..,\"all\":..,\"minlength\":..}) received on <addr>, a 'host:port' TCP address or a 'unix:<path>' socket, responding in the --format json record shape"
+    )]
+    serve: Option<String>,
+
     #[arg(required = false)]
     text: Vec<String>,
 }
@@ -106,13 +171,27 @@ fn main() {
     if args.quick {
         builder.with_low_accuracy_mode();
     }
-    if args.preload {
+    if args.preload || args.serve.is_some() {
         builder.with_preloaded_language_models();
     }
     if let Some(minimum_relative_distance) = args.minimum_relative_distance {
         builder.with_minimum_relative_distance(minimum_relative_distance);
     }
     let detector = builder.build();
+    let hints = hints::Hints::new(args.hint_lang.as_deref(), args.hint_tld.as_deref());
+
+    if let Some(addr) = &args.serve {
+        let options = serve::ServeOptions {
+            hints: &hints,
+            top_n: args.top_n,
+            reliability_threshold: args.reliability_threshold,
+            minimum_relative_distance: args
+                .minimum_relative_distance
+                .unwrap_or(DEFAULT_MINIMUM_RELATIVE_DISTANCE),
+        };
+        serve::serve(addr, &detector, &options).expect("server failed");
+        return;
+    }
 
     if !args.text.is_empty() {
         //text provided as arguments
@@ -120,82 +199,158 @@ fn main() {
         if args.minlength.is_none() || long_enough(&text, args.minlength.unwrap()) {
             if args.multi {
                 let results = detector.detect_multiple_languages_of(&text);
-                print_with_offset(&results, &text, &args.delimiter)
+                print_with_offset(
+                    &results,
+                    &text,
+                    &args.delimiter,
+                    args.format,
+                    args.top_n,
+                    args.reliability_threshold,
+                    args.minimum_relative_distance.unwrap_or(DEFAULT_MINIMUM_RELATIVE_DISTANCE),
+                )
             } else {
-                let results = detector.compute_language_confidence_values(text);
-                print_confidence_values(&results, &args.delimiter, args.confidence, args.all);
+                let results = hints.apply(detector.compute_language_confidence_values(text));
+                print_confidence_values(
+                    &results,
+                    &args.delimiter,
+                    args.confidence,
+                    args.all,
+                    args.format,
+                );
             }
         } else {
             print!("unknown{}\n", &args.delimiter);
         }
     } else if args.per_line && args.parallel {
-        let stdin = io::stdin();
-        let lines: Vec<_> = stdin
-            .lock()
+        let mut buf: Vec<u8> = Vec::new();
+        io::stdin()
+            .read_to_end(&mut buf)
+            .expect("expected input via stdin");
+        let (text, detected_encoding) = encoding::decode(&buf, &args.encoding);
+        let hints = hints.with_encoding(Some(&detected_encoding));
+        let lines: Vec<_> = text
             .lines()
-            .filter_map(|x| {
-                if let Ok(line) = x {
-                    if args.minlength.is_some() && !long_enough(&line, args.minlength.unwrap()) {
-                        None
-                    } else {
-                        Some(line)
-                    }
-                } else {
-                    None
-                }
-            })
+            .filter(|line| args.minlength.is_none() || long_enough(line, args.minlength.unwrap()))
+            .map(|line| line.to_string())
+            .collect();
+        let results: Vec<_> = detector
+            .compute_language_confidence_values_in_parallel(&lines)
+            .into_iter()
+            .map(|results| hints.apply(results))
             .collect();
-        let results = detector.compute_language_confidence_values_in_parallel(&lines);
         if args.minlength.is_some() {
             eprintln!("Note: Lines that do not match the minimum length will not be returned (disable parallel mode if you want to return them as 'unknown')")
         }
+        let mut json_results: Vec<format::LineResult> = Vec::new();
         for (line, results) in lines.iter().zip(results) {
-            print_line_with_confidence_values(
-                line,
-                &results,
-                &args.delimiter,
-                args.confidence,
-                args.all,
-            );
+            match args.format {
+                OutputFormat::Text => print_line_with_confidence_values(
+                    line,
+                    &results,
+                    &args.delimiter,
+                    args.confidence,
+                    args.all,
+                ),
+                OutputFormat::Json => {
+                    json_results.push(format::line_result(line, &results, args.confidence, args.all))
+                }
+                OutputFormat::Jsonl => format::print_json_line(&format::line_result(
+                    line,
+                    &results,
+                    args.confidence,
+                    args.all,
+                )),
+            }
+        }
+        if args.format == OutputFormat::Json {
+            format::print_json_line(&json_results);
         }
     } else if args.per_line {
-        let stdin = io::stdin();
-        for line in stdin.lock().lines() {
-            if let Ok(line) = line {
-                if args.minlength.is_none() || long_enough(&line, args.minlength.unwrap()) {
-                    let results = detector.compute_language_confidence_values(&line);
-                    print_line_with_confidence_values(
-                        &line,
+        let mut buf: Vec<u8> = Vec::new();
+        io::stdin()
+            .read_to_end(&mut buf)
+            .expect("expected input via stdin");
+        let (text, detected_encoding) = encoding::decode(&buf, &args.encoding);
+        let hints = hints.with_encoding(Some(&detected_encoding));
+        let mut json_results: Vec<format::LineResult> = Vec::new();
+        for line in text.lines() {
+            if args.minlength.is_none() || long_enough(line, args.minlength.unwrap()) {
+                let results = hints.apply(detector.compute_language_confidence_values(line));
+                match args.format {
+                    OutputFormat::Text => print_line_with_confidence_values(
+                        line,
                         &results,
                         &args.delimiter,
                         args.confidence,
                         args.all,
-                    );
-                } else {
-                    print!("unknown{}{}{}\n", &args.delimiter, &args.delimiter, line);
+                    ),
+                    OutputFormat::Json => json_results.push(format::line_result(
+                        line,
+                        &results,
+                        args.confidence,
+                        args.all,
+                    )),
+                    OutputFormat::Jsonl => format::print_json_line(&format::line_result(
+                        line,
+                        &results,
+                        args.confidence,
+                        args.all,
+                    )),
+                }
+            } else {
+                match args.format {
+                    OutputFormat::Text => {
+                        print!("unknown{}{}{}\n", &args.delimiter, &args.delimiter, line)
+                    }
+                    OutputFormat::Json => json_results.push(format::LineResult {
+                        line: line.to_string(),
+                        languages: Vec::new(),
+                    }),
+                    OutputFormat::Jsonl => format::print_json_line(&format::LineResult {
+                        line: line.to_string(),
+                        languages: Vec::new(),
+                    }),
                 }
             }
         }
+        if args.format == OutputFormat::Json {
+            format::print_json_line(&json_results);
+        }
     } else {
         let mut buf: Vec<u8> = Vec::new();
         io::stdin()
             .read_to_end(&mut buf)
             .expect("expected input via stdin");
-        let text = String::from_utf8(buf).expect("Input should be valid utf-8");
+        let (text, detected_encoding) = encoding::decode(&buf, &args.encoding);
         if args.minlength.is_none() || long_enough(&text, args.minlength.unwrap()) {
             if args.multi {
                 let results = detector.detect_multiple_languages_of(&text);
-                print_with_offset(&results, &text, &args.delimiter)
+                print_with_offset(
+                    &results,
+                    &text,
+                    &args.delimiter,
+                    args.format,
+                    args.top_n,
+                    args.reliability_threshold,
+                    args.minimum_relative_distance.unwrap_or(DEFAULT_MINIMUM_RELATIVE_DISTANCE),
+                )
             } else {
-                let results = detector.compute_language_confidence_values(text);
-                print_confidence_values(&results, &args.delimiter, args.confidence, args.all);
+                let hints = hints.with_encoding(Some(&detected_encoding));
+                let results = hints.apply(detector.compute_language_confidence_values(text));
+                print_confidence_values(
+                    &results,
+                    &args.delimiter,
+                    args.confidence,
+                    args.all,
+                    args.format,
+                );
             }
         }
     }
 }
 
 #[inline]
-fn long_enough(line: &str, minlength: u8) -> bool {
+pub(crate) fn long_enough(line: &str, minlength: u8) -> bool {
     line.chars().filter(|c| c.is_alphabetic()).count() >= minlength as usize
 }
 
@@ -204,19 +359,19 @@ fn print_confidence_values(
     delimiter: &str,
     confidence_threshold: Option<f64>,
     all: bool,
+    format: OutputFormat,
 ) {
-    let mut found = false;
-    for result in results {
-        if confidence_threshold.is_some() && result.1 >= confidence_threshold.unwrap() {
-            found = true;
-            print!("{}{}{}\n", result.0.iso_code_639_1(), delimiter, result.1);
-        }
-        if !all {
-            break;
-        }
+    let scores = format::language_scores(results, confidence_threshold, all);
+    if format != OutputFormat::Text {
+        format::print_json_line(&format::TextResult { languages: scores });
+        return;
     }
-    if !found {
+    if scores.is_empty() {
         print!("unknown{}\n", delimiter);
+    } else {
+        for score in &scores {
+            print!("{}{}{}\n", score.iso_code, delimiter, score.confidence);
+        }
     }
 }
 
@@ -227,26 +382,57 @@ fn print_line_with_confidence_values(
     confidence_threshold: Option<f64>,
     all: bool,
 ) {
-    for result in results {
-        if confidence_threshold.is_some() && result.1 >= confidence_threshold.unwrap() {
+    let scores = format::language_scores(results, confidence_threshold, all);
+    if scores.is_empty() {
+        print!("unknown{}{}{}\n", delimiter, delimiter, line);
+    } else {
+        for score in &scores {
             print!(
                 "{}{}{}{}{}\n",
-                result.0.iso_code_639_1(),
-                delimiter,
-                result.1,
-                delimiter,
-                line
+                score.iso_code, delimiter, score.confidence, delimiter, line
             );
-        } else {
-            print!("unknown{}{}{}\n", delimiter, delimiter, line);
-        }
-        if !all {
-            break;
         }
     }
 }
 
-fn print_with_offset(results: &Vec<DetectionResult>, text: &str, delimiter: &str) {
+fn print_with_offset(
+    results: &Vec<DetectionResult>,
+    text: &str,
+    delimiter: &str,
+    format: OutputFormat,
+    top_n: usize,
+    reliability_threshold: f64,
+    minimum_relative_distance: f64,
+) {
+    let summary = reliability::summarize(
+        results,
+        text,
+        top_n,
+        reliability_threshold,
+        minimum_relative_distance,
+    );
+
+    if format != OutputFormat::Text {
+        let segments: Vec<format::MultiSegment> = results
+            .iter()
+            .map(|result| format::MultiSegment {
+                start_index: result.start_index(),
+                end_index: result.end_index(),
+                iso_code: result.language().iso_code_639_1().to_string(),
+                text: text[result.start_index()..result.end_index()].to_string(),
+            })
+            .collect();
+        match format {
+            OutputFormat::Jsonl => {
+                for segment in &segments {
+                    format::print_json_line(&format::MultiSegmentRecord::new(segment));
+                }
+                format::print_json_line(&format::SummaryRecord::new(summary));
+            }
+            _ => format::print_json_line(&format::MultiResult { segments, summary }),
+        }
+        return;
+    }
     for result in results {
         print!(
             "{}{}{}{}{}{}{}\n",
@@ -259,4 +445,14 @@ fn print_with_offset(results: &Vec<DetectionResult>, text: &str, delimiter: &str
             &text[result.start_index()..result.end_index()],
         );
     }
+    let breakdown = summary
+        .languages
+        .iter()
+        .map(|language| format!("{}:{:.1}", language.iso_code, language.percent))
+        .collect::<Vec<_>>()
+        .join(",");
+    print!(
+        "summary{}{}{}{}\n",
+        delimiter, summary.reliable, delimiter, breakdown
+    );
 }
@@ -0,0 +1,82 @@
+//! Aggregate summary for `--multi` output, modelled after CLD2's
+//! `ExtDetectLanguageSummary`: how much of the text each detected
+//! language covers, and whether the overall classification should be
+//! trusted.
+
+use lingua::DetectionResult;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct LanguageCoverage {
+    pub iso_code: String,
+    pub percent: f64,
+}
+
+#[derive(Serialize)]
+pub struct MultiSummary {
+    pub reliable: bool,
+    pub languages: Vec<LanguageCoverage>,
+}
+
+/// Builds a `MultiSummary` from the segments returned by
+/// `detect_multiple_languages_of`, reporting the top `top_n` languages by
+/// their share of the text's alphabetic characters.
+///
+/// Multi-language segments don't carry a per-segment confidence value,
+/// so `reliable` instead requires the top language's coverage to clear
+/// `reliability_threshold` and its lead over the runner-up to exceed
+/// `minimum_relative_distance`.
+pub fn summarize(
+    results: &[DetectionResult],
+    text: &str,
+    top_n: usize,
+    reliability_threshold: f64,
+    minimum_relative_distance: f64,
+) -> MultiSummary {
+    let total_alphabetic: usize = text.chars().filter(|c| c.is_alphabetic()).count();
+
+    let mut coverage: Vec<(String, usize)> = Vec::new();
+    for result in results {
+        let segment = &text[result.start_index()..result.end_index()];
+        let alphabetic = segment.chars().filter(|c| c.is_alphabetic()).count();
+        let iso_code = result.language().iso_code_639_1().to_string();
+        match coverage.iter_mut().find(|(code, _)| *code == iso_code) {
+            Some(entry) => entry.1 += alphabetic,
+            None => coverage.push((iso_code, alphabetic)),
+        }
+    }
+    coverage.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let percent_of = |count: usize| {
+        if total_alphabetic > 0 {
+            count as f64 / total_alphabetic as f64 * 100.0
+        } else {
+            0.0
+        }
+    };
+
+    // Computed from the full (untruncated) coverage so that `--top-n`,
+    // which only limits how many languages are reported, can't change
+    // the reliability verdict itself.
+    let reliable = match coverage.as_slice() {
+        [] => false,
+        [(_, top_count)] => percent_of(*top_count) / 100.0 >= reliability_threshold,
+        [(_, top_count), (_, runner_up_count), ..] => {
+            let top_percent = percent_of(*top_count);
+            let runner_up_percent = percent_of(*runner_up_count);
+            top_percent / 100.0 >= reliability_threshold
+                && (top_percent - runner_up_percent) / top_percent > minimum_relative_distance
+        }
+    };
+
+    let languages: Vec<LanguageCoverage> = coverage
+        .into_iter()
+        .take(top_n)
+        .map(|(iso_code, count)| LanguageCoverage {
+            iso_code,
+            percent: percent_of(count),
+        })
+        .collect();
+
+    MultiSummary { reliable, languages }
+}
@@ -0,0 +1,161 @@
+//! Persistent server mode (`--serve`): builds the detector once and then
+//! answers newline-delimited JSON requests over a TCP or Unix-domain
+//! socket, so callers can amortize model loading across many short
+//! texts instead of paying it on every invocation. Any line-oriented
+//! socket client (e.g. `nc`) can act as the counterpart; no bundled
+//! `--connect` client is provided.
+
+use crate::format;
+use crate::hints::Hints;
+use crate::{long_enough, reliability};
+use lingua::LanguageDetector;
+use serde::Deserialize;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+
+/// The startup-time options that would otherwise be fixed per CLI
+/// invocation (`--hint-*`, `--top-n`, `--reliability-threshold`,
+/// `--minimum-relative-distance`), applied to every request a served
+/// connection receives.
+pub struct ServeOptions<'a> {
+    pub hints: &'a Hints,
+    pub top_n: usize,
+    pub reliability_threshold: f64,
+    pub minimum_relative_distance: f64,
+}
+
+/// One newline-delimited JSON request.
+#[derive(Deserialize)]
+struct Request {
+    text: String,
+    #[serde(default)]
+    multi: bool,
+    confidence: Option<f64>,
+    #[serde(default)]
+    all: bool,
+    minlength: Option<u8>,
+}
+
+/// Runs the server loop, blocking forever. `addr` is a `host:port` for
+/// TCP, or a `unix:<path>` filesystem path for a Unix-domain socket.
+pub fn serve(addr: &str, detector: &LanguageDetector, options: &ServeOptions) -> io::Result<()> {
+    match addr.strip_prefix("unix:") {
+        Some(path) => serve_unix(path, detector, options),
+        None => serve_tcp(addr, detector, options),
+    }
+}
+
+fn serve_tcp(addr: &str, detector: &LanguageDetector, options: &ServeOptions) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    eprintln!("Listening on tcp://{}", addr);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let peer = stream.try_clone()?;
+        if let Err(err) = handle_connection(stream, peer, detector, options) {
+            eprintln!("Connection error: {}", err);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn serve_unix(path: &str, detector: &LanguageDetector, options: &ServeOptions) -> io::Result<()> {
+    use std::os::unix::net::UnixListener;
+
+    let _ = std::fs::remove_file(path);
+    let listener = UnixListener::bind(path)?;
+    eprintln!("Listening on unix://{}", path);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let peer = stream.try_clone()?;
+        if let Err(err) = handle_connection(stream, peer, detector, options) {
+            eprintln!("Connection error: {}", err);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve_unix(_path: &str, _detector: &LanguageDetector, _options: &ServeOptions) -> io::Result<()> {
+    panic!("Unix-domain sockets (unix:<path>) are only supported on Unix platforms");
+}
+
+fn handle_connection<R: Read, W: Write>(
+    reader: R,
+    mut writer: W,
+    detector: &LanguageDetector,
+    options: &ServeOptions,
+) -> io::Result<()> {
+    for line in BufReader::new(reader).lines() {
+        let response = match line {
+            Ok(line) if line.trim().is_empty() => continue,
+            Ok(line) => match serde_json::from_str::<Request>(&line) {
+                Ok(request) => handle_request(request, detector, options),
+                Err(err) => {
+                    serde_json::to_string(&serde_json::json!({ "error": err.to_string() }))
+                        .expect("error response should serialize")
+                }
+            },
+            // A single malformed (e.g. non-UTF-8) line shouldn't kill the
+            // whole connection; report it like a JSON-parse error and keep
+            // reading.
+            Err(err) => serde_json::to_string(&serde_json::json!({ "error": err.to_string() }))
+                .expect("error response should serialize"),
+        };
+        writeln!(writer, "{}", response)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+fn handle_request(request: Request, detector: &LanguageDetector, options: &ServeOptions) -> String {
+    let too_short = request
+        .minlength
+        .map_or(false, |minlength| !long_enough(&request.text, minlength));
+
+    if request.multi {
+        if too_short {
+            let summary = reliability::summarize(
+                &[],
+                &request.text,
+                options.top_n,
+                options.reliability_threshold,
+                options.minimum_relative_distance,
+            );
+            return serde_json::to_string(&format::MultiResult {
+                segments: Vec::new(),
+                summary,
+            })
+            .expect("result should be serializable to JSON");
+        }
+        let results = detector.detect_multiple_languages_of(&request.text);
+        let segments: Vec<format::MultiSegment> = results
+            .iter()
+            .map(|result| format::MultiSegment {
+                start_index: result.start_index(),
+                end_index: result.end_index(),
+                iso_code: result.language().iso_code_639_1().to_string(),
+                text: request.text[result.start_index()..result.end_index()].to_string(),
+            })
+            .collect();
+        let summary = reliability::summarize(
+            &results,
+            &request.text,
+            options.top_n,
+            options.reliability_threshold,
+            options.minimum_relative_distance,
+        );
+        serde_json::to_string(&format::MultiResult { segments, summary })
+            .expect("result should be serializable to JSON")
+    } else if too_short {
+        serde_json::to_string(&format::TextResult { languages: Vec::new() })
+            .expect("result should be serializable to JSON")
+    } else {
+        let results = options
+            .hints
+            .apply(detector.compute_language_confidence_values(request.text.clone()));
+        let languages = format::language_scores(&results, request.confidence, request.all);
+        serde_json::to_string(&format::TextResult { languages })
+            .expect("result should be serializable to JSON")
+    }
+}